@@ -0,0 +1,549 @@
+//! Parses the `#[derive(OnlyArgs)]` input into a small AST that the rest of the crate turns into
+//! code.
+
+use proc_macro2::Ident;
+use syn::{
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+    Data, DeriveInput, Fields, GenericArgument, Lit, LitStr, Meta, Path, PathArguments, Token, Type,
+};
+
+/// The parsed contents of a `#[derive(OnlyArgs)]` struct.
+pub struct ArgumentStruct {
+    pub name: Ident,
+    pub doc: Vec<String>,
+    pub flags: Vec<ArgFlag>,
+    pub options: Vec<ArgOption>,
+    pub positional: Option<ArgOption>,
+    /// Whether `#[onlyargs(completion)]` was present, opting into the generated `completion()`
+    /// method.
+    pub completion: bool,
+}
+
+/// A boolean flag, e.g. `--verbose`/`-v`.
+pub struct ArgFlag {
+    pub name: Ident,
+    pub short: Option<char>,
+    pub doc: Vec<String>,
+    /// Whether this flag corresponds to a real struct field. The built-in `--help`/`--version`
+    /// flags are not.
+    pub output: bool,
+    /// Whether repeated occurrences accumulate (`Count<T>`, e.g. `-vvv`) instead of the flag
+    /// simply being `true`/`false`.
+    pub counted: bool,
+}
+
+/// An option that carries a value, e.g. `--output <path>`.
+pub struct ArgOption {
+    pub name: Ident,
+    pub short: Option<char>,
+    pub doc: Vec<String>,
+    pub ty_help: ArgType,
+    /// Whether this field is `Option<T>`, i.e. not required.
+    pub optional: bool,
+    /// The fallback literal from `#[default(...)]`, used in place of `.required(...)` when the
+    /// argument is absent.
+    pub default: Option<Lit>,
+    /// The predicate path and failure message from `#[guard(path::to_fn, "message")]`, run on the
+    /// parsed value before it's accepted.
+    pub guard: Option<(Path, LitStr)>,
+}
+
+/// The "primitive" type backing an option, used to pick the right parsing extension method and
+/// help text hint.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ArgType {
+    Bool,
+    Number,
+    OsString,
+    Path,
+    String,
+}
+
+impl ArgType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArgType::Bool => "",
+            ArgType::Number => " <NUM>",
+            ArgType::OsString | ArgType::String => " <STR>",
+            ArgType::Path => " <PATH>",
+        }
+    }
+}
+
+/// A borrowed, uniform view over [`ArgFlag`] and [`ArgOption`] used for help text generation and
+/// short-name de-duplication.
+pub struct ArgView<'a> {
+    pub name: &'a Ident,
+    pub short: Option<char>,
+    pub doc: &'a [String],
+    pub ty_help: ArgType,
+}
+
+impl ArgFlag {
+    pub fn as_view(&self) -> ArgView {
+        ArgView {
+            name: &self.name,
+            short: self.short,
+            doc: &self.doc,
+            ty_help: ArgType::Bool,
+        }
+    }
+}
+
+impl ArgOption {
+    pub fn as_view(&self) -> ArgView {
+        ArgView {
+            name: &self.name,
+            short: self.short,
+            doc: &self.doc,
+            ty_help: self.ty_help,
+        }
+    }
+}
+
+/// The two shapes `#[derive(OnlyArgs)]` understands: a flat struct of flags/options, or an enum
+/// of subcommands, each variant wrapping a single field whose type itself derives `OnlyArgs`.
+pub enum OnlyArgsInput {
+    Struct(ArgumentStruct),
+    Subcommands(SubcommandEnum),
+}
+
+impl Parse for OnlyArgsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let input: DeriveInput = input.parse()?;
+
+        match input.data {
+            Data::Enum(_) => Ok(OnlyArgsInput::Subcommands(
+                SubcommandEnum::from_derive_input(input)?,
+            )),
+            _ => Ok(OnlyArgsInput::Struct(ArgumentStruct::from_derive_input(
+                input,
+            )?)),
+        }
+    }
+}
+
+/// One `variant(InnerArgs)` of a subcommand enum.
+pub struct SubcommandVariant {
+    pub name: Ident,
+    pub ty: Type,
+    pub doc: Vec<String>,
+}
+
+/// The parsed contents of a `#[derive(OnlyArgs)]` enum, dispatching on a leading verb.
+pub struct SubcommandEnum {
+    pub name: Ident,
+    pub doc: Vec<String>,
+    pub variants: Vec<SubcommandVariant>,
+    /// Whether `#[onlyargs(completion)]` was present, opting into the generated `completion()`
+    /// method.
+    pub completion: bool,
+}
+
+impl SubcommandEnum {
+    fn from_derive_input(input: DeriveInput) -> syn::Result<Self> {
+        let name = input.ident;
+        let doc = doc_comment(&input.attrs);
+        let completion = completion_attr(&input.attrs)?;
+
+        let Data::Enum(data) = input.data else {
+            unreachable!("caller already matched on Data::Enum");
+        };
+
+        let variants = data
+            .variants
+            .into_iter()
+            .map(|variant| {
+                let variant_doc = doc_comment(&variant.attrs);
+                let Fields::Unnamed(fields) = variant.fields else {
+                    return Err(syn::Error::new(
+                        variant.ident.span(),
+                        "each subcommand variant must wrap a single `OnlyArgs` struct, e.g. `Add(AddArgs)`",
+                    ));
+                };
+                let mut fields = fields.unnamed.into_iter();
+                let field = fields.next().ok_or_else(|| {
+                    syn::Error::new(
+                        variant.ident.span(),
+                        "each subcommand variant must wrap a single `OnlyArgs` struct, e.g. `Add(AddArgs)`",
+                    )
+                })?;
+                if fields.next().is_some() {
+                    return Err(syn::Error::new(
+                        variant.ident.span(),
+                        "subcommand variants can only wrap a single field",
+                    ));
+                }
+
+                Ok(SubcommandVariant {
+                    name: variant.ident,
+                    ty: field.ty,
+                    doc: variant_doc,
+                })
+            })
+            .collect::<syn::Result<_>>()?;
+
+        Ok(SubcommandEnum {
+            name,
+            doc,
+            variants,
+            completion,
+        })
+    }
+}
+
+impl ArgumentStruct {
+    fn from_derive_input(input: DeriveInput) -> syn::Result<Self> {
+        let name = input.ident;
+        let doc = doc_comment(&input.attrs);
+        let completion = completion_attr(&input.attrs)?;
+
+        let fields = match input.data {
+            Data::Struct(data) => match data.fields {
+                Fields::Named(fields) => fields.named,
+                fields => {
+                    return Err(syn::Error::new(
+                        fields.span(),
+                        "`OnlyArgs` can only be derived for structs with named fields",
+                    ))
+                }
+            },
+            _ => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`OnlyArgs` can only be derived for structs",
+                ))
+            }
+        };
+
+        let mut flags = vec![];
+        let mut options = vec![];
+        let mut positional = None;
+
+        for field in fields {
+            let field_name = field.ident.expect("named field");
+            let field_doc = doc_comment(&field.attrs);
+            let short = default_short(&field_name);
+            let default = default_attr(&field.attrs)?;
+            let guard = guard_attr(&field.attrs)?;
+
+            match classify(&field.ty)? {
+                FieldKind::Flag => {
+                    if default.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[default]` is not supported on `bool` flags",
+                        ));
+                    }
+                    if guard.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[guard]` is not supported on `bool` flags",
+                        ));
+                    }
+
+                    flags.push(ArgFlag {
+                        name: field_name,
+                        short,
+                        doc: field_doc,
+                        output: true,
+                        counted: false,
+                    })
+                }
+                FieldKind::Count => {
+                    if default.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[default]` is not supported on `Count<T>` fields",
+                        ));
+                    }
+                    if guard.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[guard]` is not supported on `Count<T>` fields",
+                        ));
+                    }
+
+                    flags.push(ArgFlag {
+                        name: field_name,
+                        short,
+                        doc: field_doc,
+                        output: true,
+                        counted: true,
+                    })
+                }
+                FieldKind::Option { ty_help, optional } => {
+                    if default.is_some() && optional {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[default]` cannot be combined with `Option<T>`; the field is already optional",
+                        ));
+                    }
+                    if let Some(lit) = &default {
+                        validate_default_literal(&field.ty, lit)?;
+                    }
+
+                    options.push(ArgOption {
+                        name: field_name,
+                        short,
+                        doc: field_doc,
+                        ty_help,
+                        optional,
+                        default,
+                        guard,
+                    })
+                }
+                FieldKind::Positional { ty_help } => {
+                    if positional.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "only one positional (`Vec<T>`) field is allowed",
+                        ));
+                    }
+                    if default.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[default]` is not supported on positional (`Vec<T>`) fields",
+                        ));
+                    }
+                    if guard.is_some() {
+                        return Err(syn::Error::new(
+                            field_name.span(),
+                            "`#[guard]` is not supported on positional (`Vec<T>`) fields",
+                        ));
+                    }
+
+                    positional = Some(ArgOption {
+                        name: field_name,
+                        short: None,
+                        doc: field_doc,
+                        ty_help,
+                        optional: false,
+                        default: None,
+                        guard: None,
+                    });
+                }
+            }
+        }
+
+        Ok(ArgumentStruct {
+            name,
+            doc,
+            flags,
+            options,
+            positional,
+            completion,
+        })
+    }
+}
+
+enum FieldKind {
+    Flag,
+    Count,
+    Option { ty_help: ArgType, optional: bool },
+    Positional { ty_help: ArgType },
+}
+
+fn classify(ty: &Type) -> syn::Result<FieldKind> {
+    if let Some(inner) = unwrap_generic(ty, "Vec") {
+        let ty_help = primitive_type(inner)?;
+
+        return Ok(FieldKind::Positional { ty_help });
+    }
+
+    if let Some(inner) = unwrap_generic(ty, "Count") {
+        if primitive_type(inner)? != ArgType::Number {
+            return Err(syn::Error::new(
+                ty.span(),
+                "`Count<T>` requires a numeric `T`",
+            ));
+        }
+
+        return Ok(FieldKind::Count);
+    }
+
+    if let Some(inner) = unwrap_generic(ty, "Option") {
+        let ty_help = primitive_type(inner)?;
+
+        return Ok(FieldKind::Option {
+            ty_help,
+            optional: true,
+        });
+    }
+
+    if is_bool(ty) {
+        return Ok(FieldKind::Flag);
+    }
+
+    let ty_help = primitive_type(ty)?;
+
+    Ok(FieldKind::Option {
+        ty_help,
+        optional: false,
+    })
+}
+
+fn primitive_type(ty: &Type) -> syn::Result<ArgType> {
+    let name = match type_name(ty) {
+        Some(name) => name,
+        None => return Err(syn::Error::new(ty.span(), "unsupported argument type")),
+    };
+
+    match name.as_str() {
+        "bool" => Ok(ArgType::Bool),
+        "f32" | "f64" | "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "i128"
+        | "u128" | "isize" | "usize" => Ok(ArgType::Number),
+        "OsString" => Ok(ArgType::OsString),
+        "PathBuf" => Ok(ArgType::Path),
+        "String" => Ok(ArgType::String),
+        _ => Err(syn::Error::new(ty.span(), "unsupported argument type")),
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    type_name(ty).as_deref() == Some("bool")
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|seg| seg.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// If `ty` is `wrapper<T>`, returns `T`.
+fn unwrap_generic<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+
+    if segment.ident != wrapper {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// The default short name: the first ASCII alphabetic character of the field name.
+fn default_short(name: &Ident) -> Option<char> {
+    let ch = name
+        .to_string()
+        .chars()
+        .find(|ch| ch.is_ascii_alphabetic())?;
+
+    Some(ch.to_ascii_lowercase())
+}
+
+/// Parses the struct/enum-level `#[onlyargs(completion)]` attribute, if present.
+///
+/// The generated `completion()` method references `onlyargs::Shell`, which does not yet exist in
+/// the `onlyargs` crate this derive targets; gating it behind this opt-in keeps every other
+/// derive user compiling until that type lands upstream.
+fn completion_attr(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("onlyargs")) else {
+        return Ok(false);
+    };
+
+    let mut completion = false;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("completion") {
+            completion = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported `onlyargs` attribute"))
+        }
+    })?;
+
+    Ok(completion)
+}
+
+/// Parses a `#[default(literal)]` attribute, if present, into its fallback literal.
+///
+/// Restricted to literals (rather than arbitrary expressions) so the value can also be rendered
+/// cleanly into the generated `[default: ...]` help text without round-tripping through a
+/// token-stream pretty-printer.
+fn default_attr(attrs: &[syn::Attribute]) -> syn::Result<Option<Lit>> {
+    attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("default"))
+        .map(|attr| attr.parse_args::<Lit>())
+        .transpose()
+}
+
+/// Checks a parsed `#[default(literal)]` against the field's declared type, so a mismatch (e.g.
+/// `#[default(8080)]` on an `f64` field) is reported as a clean error here instead of surfacing as
+/// a raw `E0308` inside the generated `unwrap_or(...)` call.
+fn validate_default_literal(ty: &Type, lit: &Lit) -> syn::Result<()> {
+    let name = type_name(ty).unwrap_or_default();
+    let ok = match (name.as_str(), lit) {
+        ("f32" | "f64", Lit::Float(_)) => true,
+        (
+            "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "i128" | "u128"
+            | "isize" | "usize",
+            Lit::Int(_),
+        ) => true,
+        ("String" | "OsString" | "PathBuf", Lit::Str(_)) => true,
+        _ => false,
+    };
+
+    if ok {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            lit.span(),
+            format!("`#[default]` literal does not match the field's `{name}` type"),
+        ))
+    }
+}
+
+/// Parses a `#[guard(path::to_fn, "message")]` attribute, if present, into the predicate path and
+/// the message to report when it returns `false`.
+fn guard_attr(attrs: &[syn::Attribute]) -> syn::Result<Option<(Path, LitStr)>> {
+    attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("guard"))
+        .map(|attr| {
+            attr.parse_args_with(|input: ParseStream| {
+                let path: Path = input.parse()?;
+                input.parse::<Token![,]>()?;
+                let message: LitStr = input.parse()?;
+
+                Ok((path, message))
+            })
+        })
+        .transpose()
+}
+
+/// Collects `///` doc comments (desugared to `#[doc = "..."]`) into a list of lines.
+fn doc_comment(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| {
+            let Meta::NameValue(meta) = &attr.meta else {
+                return None;
+            };
+
+            if !meta.path.is_ident("doc") {
+                return None;
+            }
+
+            let syn::Expr::Lit(expr) = &meta.value else {
+                return None;
+            };
+            let Lit::Str(lit) = &expr.lit else {
+                return None;
+            };
+
+            Some(lit.value().trim().to_string())
+        })
+        .collect()
+}